@@ -73,8 +73,8 @@ use std::hash;
 use std::kinds::{Send,Sized,marker};
 use std::mem;
 use std::prelude::{Drop, Eq, Iterator};
-use std::prelude::{None, Option, Ord, Ordering, PartialEq};
-use std::prelude::{PartialEqSlicePrelude, PartialOrd, RawPtr, Some};
+use std::prelude::{Err, None, Ok, Option, Ord, Ordering, PartialEq};
+use std::prelude::{PartialEqSlicePrelude, PartialOrd, RawPtr, Result, Some};
 use std::prelude::{SlicePrelude, StrPrelude, Vec};
 use std::ptr;
 use std::raw::Slice;
@@ -145,6 +145,16 @@ impl Ord for CStrBuf {
 
 impl Eq for CStrBuf {}
 
+impl<S: hash::Writer> hash::Hash<S> for CStrBuf {
+    fn hash(&self, state: &mut S) {
+        let len = unsafe { c_strlen(self.ptr) };
+        let bytes: &[u8] = unsafe {
+            mem::transmute(Slice { data: self.ptr as *const u8, len: len })
+        };
+        bytes.hash(state)
+    }
+}
+
 impl PartialEq for CString {
     #[inline]
     fn eq(&self, other: &CString) -> bool {
@@ -175,6 +185,297 @@ impl<S: hash::Writer> hash::Hash<S> for CString {
     }
 }
 
+impl<'a, 'b> PartialEq<BorrowedCString<'b>> for BorrowedCString<'a> {
+    #[inline]
+    fn eq(&self, other: &BorrowedCString<'b>) -> bool {
+        self.as_bytes_no_nul().eq(other.as_bytes_no_nul())
+    }
+}
+
+impl<'a> Eq for BorrowedCString<'a> {}
+
+impl<'a, 'b> PartialOrd<BorrowedCString<'b>> for BorrowedCString<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &BorrowedCString<'b>) -> Option<Ordering> {
+        self.as_bytes_no_nul().partial_cmp(other.as_bytes_no_nul())
+    }
+}
+
+impl<'a> Ord for BorrowedCString<'a> {
+    #[inline]
+    fn cmp(&self, other: &BorrowedCString<'a>) -> Ordering {
+        self.as_bytes_no_nul().cmp(other.as_bytes_no_nul())
+    }
+}
+
+impl<'a, S: hash::Writer> hash::Hash<S> for BorrowedCString<'a> {
+    #[inline]
+    fn hash(&self, state: &mut S) {
+        self.as_bytes_no_nul().hash(state)
+    }
+}
+
+// Extracts the NUL-free byte content of a C-string type or a native
+// Rust string/byte-string type, so cross-type comparisons can all be
+// expressed as a single byte-slice comparison.
+trait AsByteSlice {
+    fn as_byte_slice(&self) -> &[u8];
+}
+
+impl AsByteSlice for CString {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] { self.as_bytes_no_nul() }
+}
+
+impl AsByteSlice for CStrBuf {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] {
+        unsafe {
+            let len = c_strlen(self.ptr);
+            mem::transmute(Slice { data: self.ptr as *const u8, len: len })
+        }
+    }
+}
+
+impl<'a> AsByteSlice for BorrowedCString<'a> {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] { self.as_bytes_no_nul() }
+}
+
+impl AsByteSlice for str {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] { self.as_bytes() }
+}
+
+impl AsByteSlice for [u8] {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] { self }
+}
+
+impl AsByteSlice for String {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] { self.as_bytes() }
+}
+
+impl AsByteSlice for Vec<u8> {
+    #[inline]
+    fn as_byte_slice(&self) -> &[u8] { self.as_slice() }
+}
+
+// Generates the symmetric `PartialEq`/`PartialOrd` pair between a
+// C-string type and a native Rust string/byte-string type, comparing
+// the NUL-free byte content of both sides. `$lt` carries any lifetime
+// parameters the two types need (e.g. the `BorrowedCString`'s own
+// lifetime, or a reference `$rhs`'s lifetime).
+macro_rules! impl_cross_cmp(
+    ($($lt:tt),*; $lhs:ty, $rhs:ty) => (
+        impl<$($lt),*> PartialEq<$rhs> for $lhs {
+            #[inline]
+            fn eq(&self, other: &$rhs) -> bool {
+                self.as_byte_slice() == other.as_byte_slice()
+            }
+        }
+
+        impl<$($lt),*> PartialEq<$lhs> for $rhs {
+            #[inline]
+            fn eq(&self, other: &$lhs) -> bool {
+                self.as_byte_slice() == other.as_byte_slice()
+            }
+        }
+
+        impl<$($lt),*> PartialOrd<$rhs> for $lhs {
+            #[inline]
+            fn partial_cmp(&self, other: &$rhs) -> Option<Ordering> {
+                self.as_byte_slice().partial_cmp(other.as_byte_slice())
+            }
+        }
+
+        impl<$($lt),*> PartialOrd<$lhs> for $rhs {
+            #[inline]
+            fn partial_cmp(&self, other: &$lhs) -> Option<Ordering> {
+                self.as_byte_slice().partial_cmp(other.as_byte_slice())
+            }
+        }
+    );
+)
+
+impl_cross_cmp!('a; CString, &'a str);
+impl_cross_cmp!('a; CString, &'a [u8]);
+impl_cross_cmp!(; CString, String);
+impl_cross_cmp!(; CString, Vec<u8>);
+
+impl_cross_cmp!('a; CStrBuf, &'a str);
+impl_cross_cmp!('a; CStrBuf, &'a [u8]);
+impl_cross_cmp!(; CStrBuf, String);
+impl_cross_cmp!(; CStrBuf, Vec<u8>);
+
+impl_cross_cmp!('b, 'a; BorrowedCString<'b>, &'a str);
+impl_cross_cmp!('b, 'a; BorrowedCString<'b>, &'a [u8]);
+impl_cross_cmp!('b; BorrowedCString<'b>, String);
+impl_cross_cmp!('b; BorrowedCString<'b>, Vec<u8>);
+
+/// An error returned from a fallible C-string construction when the
+/// source bytes contain an interior NUL byte.
+///
+/// The error carries the offset of the offending byte and hands back
+/// the bytes that were rejected, so the caller can recover instead of
+/// losing the input.
+pub struct NulError {
+    position: uint,
+    bytes: Vec<u8>,
+}
+
+impl NulError {
+    /// Returns the byte offset of the first interior NUL found in the
+    /// input.
+    pub fn nul_position(&self) -> uint { self.position }
+
+    /// Consumes the error, returning the bytes that were rejected.
+    pub fn into_bytes(self) -> Vec<u8> { self.bytes }
+}
+
+impl fmt::Show for NulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "data provided contains an interior null byte at position {}",
+               self.position)
+    }
+}
+
+/// Alias for `NulError`, named to match `checked_to_c_str()`.
+pub type CStrError = NulError;
+
+/// The error returned by `CString::into_string` when the string's
+/// contents are not valid UTF-8.
+///
+/// Carries the original `CString` so the caller can recover it instead
+/// of losing the foreign buffer.
+pub struct CStringFromUtf8Error {
+    c_string: CString,
+}
+
+impl CStringFromUtf8Error {
+    /// Consumes this error, returning the original `CString` that was
+    /// not valid UTF-8.
+    pub fn into_c_string(self) -> CString { self.c_string }
+}
+
+impl fmt::Show for CStringFromUtf8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "C string contents are not valid UTF-8")
+    }
+}
+
+/// The error returned by `CString::from_vec_with_nul` when the given
+/// vector is not a valid NUL-terminated, interior-NUL-free C string
+/// body.
+///
+/// Carries the rejected vector back via `into_bytes()` so the caller
+/// can recover it.
+pub struct FromVecWithNulError {
+    kind: FromVecWithNulErrorKind,
+    bytes: Vec<u8>,
+}
+
+enum FromVecWithNulErrorKind {
+    NotNulTerminated,
+    InteriorNul(uint),
+}
+
+impl FromVecWithNulError {
+    /// Consumes this error, returning the rejected vector.
+    pub fn into_bytes(self) -> Vec<u8> { self.bytes }
+}
+
+impl fmt::Show for FromVecWithNulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            FromVecWithNulErrorKind::NotNulTerminated =>
+                write!(f, "data provided is not NUL-terminated"),
+            FromVecWithNulErrorKind::InteriorNul(pos) =>
+                write!(f, "data provided contains an interior null byte at position {}", pos),
+        }
+    }
+}
+
+// Words with every byte set to 0x01 and 0x80 respectively, built
+// portably regardless of the platform's word size.
+const NUL_LO: uint = !0u / 0xff;
+const NUL_HI: uint = NUL_LO << 7;
+
+/// Finds the offset of the first NUL byte in `v`, if any.
+///
+/// Scans whole machine words at a time using the classic zero-byte
+/// test `(w - LO) & !w & HI`: a nonzero result means some byte in the
+/// word `w` is zero. Falls back to a byte-by-byte scan for the
+/// unaligned head, the word found to contain a zero byte, and the
+/// trailing partial word.
+fn first_nul(v: &[u8]) -> Option<uint> {
+    let len = v.len();
+    let ptr = v.as_ptr();
+    let word_size = mem::size_of::<uint>();
+    let mut i = 0u;
+
+    while i < len && (ptr as uint + i) % word_size != 0 {
+        if v[i] == NUL {
+            return Some(i);
+        }
+        i += 1;
+    }
+
+    while i + word_size <= len {
+        let word = unsafe { *(ptr.offset(i as int) as *const uint) };
+        if (word.wrapping_sub(NUL_LO)) & !word & NUL_HI != 0 {
+            let mut j = i;
+            while j < i + word_size {
+                if v[j] == NUL {
+                    return Some(j);
+                }
+                j += 1;
+            }
+        }
+        i += word_size;
+    }
+
+    while i < len {
+        if v[i] == NUL {
+            return Some(i);
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Computes the length of a NUL-terminated string, replacing
+/// `libc::strlen` with the same word-at-a-time zero-byte test used by
+/// `first_nul`, since the length of the string is not known ahead of
+/// time here.
+unsafe fn c_strlen(ptr: *const libc::c_char) -> uint {
+    let start = ptr as uint;
+    let word_size = mem::size_of::<uint>();
+    let mut p = ptr as *const u8;
+
+    while (p as uint) % word_size != 0 {
+        if *p == NUL {
+            return (p as uint) - start;
+        }
+        p = p.offset(1);
+    }
+
+    loop {
+        let word = *(p as *const uint);
+        if (word.wrapping_sub(NUL_LO)) & !word & NUL_HI != 0 {
+            break;
+        }
+        p = p.offset(word_size as int);
+    }
+
+    while *p != NUL {
+        p = p.offset(1);
+    }
+    (p as uint) - start
+}
+
 fn libc_malloc(size: uint) -> *mut libc::c_char {
     let buf = unsafe {
             libc::malloc(size as libc::size_t) as *mut libc::c_char
@@ -241,7 +542,7 @@ impl CStrBuf {
     pub fn into_c_str(mut self) -> CString {
         CString {
             buf: CStrBuf { ptr: self.ptr, dtor: self.dtor.take() },
-            len: unsafe { libc::strlen(self.ptr) as uint }
+            len: unsafe { c_strlen(self.ptr) }
         }
     }
 
@@ -249,7 +550,7 @@ impl CStrBuf {
     /// Returns `None` if the string is not UTF-8.
     pub fn to_string(&self) -> Option<String> {
         unsafe {
-            let len = libc::strlen(self.ptr) as uint;
+            let len = c_strlen(self.ptr);
             let ptr = self.ptr as *const u8;
             if slice::raw::buf_as_slice(ptr, len, |v| { str::is_utf8(v) }) {
                 Some(string::raw::from_buf_len(ptr, len))
@@ -262,7 +563,7 @@ impl CStrBuf {
     /// Copies the `CStrBuf` into a vector of bytes.
     pub fn to_vec(&self) -> Vec<u8> {
         unsafe {
-            let len = libc::strlen(self.ptr) as uint;
+            let len = c_strlen(self.ptr);
             vec::raw::from_buf(self.ptr as *const u8, len)
         }
     }
@@ -297,6 +598,17 @@ impl CStrBuf {
     /// a user of `.unwrap()` should ensure the allocation is eventually
     /// freed.
     ///
+    /// The deallocation function required to free the returned pointer
+    /// is unspecified and depends on how the `CStrBuf` was constructed:
+    /// `new_libc` and most conversions from `&str`/`&[u8]` hand back a
+    /// buffer owned by `libc::free`, but `CString::from_vec_with_nul`
+    /// retains a `Vec<u8>`'s allocation and must be freed by
+    /// reconstructing and dropping that `Vec` instead. There is
+    /// currently no way to query which discipline applies to a given
+    /// value, so guessing is unsound; only call `.unwrap()` when the
+    /// construction path is known, and prefer letting the value's own
+    /// destructor run otherwise.
+    ///
     /// Prefer `.as_ptr()` when just retrieving a pointer to the
     /// string data, as that does not relinquish ownership.
     pub unsafe fn unwrap(mut self) -> *const libc::c_char {
@@ -365,6 +677,61 @@ impl CString {
         }
     }
 
+    /// Copies `bytes` into a freshly allocated `CString`.
+    ///
+    /// Returns a `NulError` if `bytes` contains an interior NUL byte,
+    /// reporting its position and handing the input bytes back so the
+    /// caller can recover.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CString, NulError> {
+        match first_nul(bytes) {
+            Some(pos) => Err(NulError { position: pos, bytes: bytes.to_vec() }),
+            None => Ok(unsafe { str_dup(bytes.as_ptr(), bytes.len()) })
+        }
+    }
+
+    /// Adopts an existing `Vec<u8>` as a `CString` without copying.
+    ///
+    /// `vec`'s last byte must be NUL and must be the only NUL byte in
+    /// the vector; otherwise the vector is handed back inside a
+    /// `FromVecWithNulError`. On success, the vector's allocation is
+    /// retained directly and freed by Rust's allocator (not
+    /// `libc::free`) when the returned `CString` is dropped.
+    pub fn from_vec_with_nul(vec: Vec<u8>) -> Result<CString, FromVecWithNulError> {
+        let len = vec.len();
+        if len == 0 || vec[len - 1] != NUL {
+            return Err(FromVecWithNulError {
+                kind: FromVecWithNulErrorKind::NotNulTerminated,
+                bytes: vec,
+            });
+        }
+        match first_nul(vec.slice_to(len - 1)) {
+            Some(pos) => Err(FromVecWithNulError {
+                kind: FromVecWithNulErrorKind::InteriorNul(pos),
+                bytes: vec,
+            }),
+            None => Ok(unsafe { CString::from_vec_with_nul_unchecked(vec) })
+        }
+    }
+
+    /// Unchecked variant of `from_vec_with_nul` that doesn't verify
+    /// `vec`'s contents are a valid NUL-terminated, NUL-free C string
+    /// body.
+    pub unsafe fn from_vec_with_nul_unchecked(vec: Vec<u8>) -> CString {
+        let full_len = vec.len();
+        let len = full_len - 1;
+        let cap = vec.capacity();
+        let ptr = vec.as_ptr() as *mut libc::c_char;
+        mem::forget(vec);
+
+        let dtor = proc(p: *mut libc::c_char) {
+            mem::drop(unsafe { Vec::from_raw_parts(p as *mut u8, full_len, cap) });
+        };
+        CString {
+            buf: CStrBuf::new_internal(ptr as *const libc::c_char, Some(dtor)),
+            len: len
+        }
+    }
+
     /// Return a pointer to the NUL-terminated string data.
     ///
     /// `.as_ptr` returns an internal pointer into the `CString`, and
@@ -431,6 +798,31 @@ impl CString {
         str::from_utf8(buf)
     }
 
+    /// Consumes the `CString`, returning its contents as a byte vector.
+    /// Does not include the terminating NUL byte.
+    pub fn into_vec(self) -> Vec<u8> {
+        unsafe { vec::raw::from_buf(self.buf.ptr as *const u8, self.len) }
+    }
+
+    /// Consumes the `CString`, returning its contents as a byte vector.
+    /// Includes the terminating NUL byte.
+    pub fn into_bytes_with_nul(self) -> Vec<u8> {
+        unsafe { vec::raw::from_buf(self.buf.ptr as *const u8, self.len + 1) }
+    }
+
+    /// Consumes the `CString`, copying its contents into a `String`.
+    ///
+    /// Returns a `CStringFromUtf8Error` if the contents are not valid
+    /// UTF-8, handing the original `CString` back so the caller can
+    /// recover it.
+    pub fn into_string(self) -> Result<String, CStringFromUtf8Error> {
+        if str::is_utf8(self.as_bytes_no_nul()) {
+            Ok(unsafe { string::raw::from_buf_len(self.buf.ptr as *const u8, self.len) })
+        } else {
+            Err(CStringFromUtf8Error { c_string: self })
+        }
+    }
+
     /// Returns an iterator over the string's bytes.
     pub fn iter<'a>(&'a self) -> CChars<'a> {
         self.buf.iter()
@@ -441,6 +833,15 @@ impl CString {
     /// a user of `.unwrap()` should ensure the allocation is eventually
     /// freed.
     ///
+    /// As with `CStrBuf::unwrap`, the deallocation function required
+    /// to free the returned pointer is unspecified and depends on how
+    /// the `CString` was constructed (`libc::free` for most
+    /// constructors, but Rust's allocator, via a reconstructed
+    /// `Vec<u8>`, for one built with `from_vec_with_nul`). There is no
+    /// supported way to tell these apart after the fact, so guessing
+    /// is unsound; only call `.unwrap()` when the construction path is
+    /// known.
+    ///
     /// Prefer `.as_ptr()` when just retrieving a pointer to the
     /// string data, as that does not relinquish ownership.
     pub unsafe fn unwrap(self) -> *const libc::c_char {
@@ -468,7 +869,7 @@ impl<'a> BorrowedCString<'a> {
         assert!(!ptr.is_null());
         BorrowedCString {
             ptr: ptr,
-            len: libc::strlen(ptr) as uint,
+            len: c_strlen(ptr),
             marker: marker::ContravariantLifetime
         }
     }
@@ -519,6 +920,60 @@ impl<'a> BorrowedCString<'a> {
 
     /// Returns true if the string is empty.
     pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Copies the `BorrowedCString` into a freshly allocated `CString`.
+    pub fn to_owned(&self) -> CString {
+        unsafe { str_dup(self.ptr as *const u8, self.len) }
+    }
+}
+
+/// Either an owned `CString` or a `BorrowedCString` pointing into
+/// memory that already outlives the call, so that an API can avoid
+/// allocating when the source pointer is already available.
+///
+/// This plays the role `std::borrow::Cow` plays for `str`/`String`,
+/// but is a hand-rolled enum rather than an impl of the standard
+/// `Borrow`/`ToOwned` pairing. That pairing doesn't fit here: `Borrow`
+/// requires `CString::borrow(&self) -> &BorrowedCString`, handing back
+/// a reference into `self`, but `CString` doesn't store a
+/// `BorrowedCString` field to borrow from, only the raw `CStrBuf` and
+/// a length, so there is nothing to return a reference to without
+/// fabricating a temporary. `str`/`String` sidestep this because `str`
+/// is an unsized slice type that `String`'s buffer can be reinterpreted
+/// as; `BorrowedCString` is a sized pointer-plus-length value, not a
+/// slice, so the same trick is not available. Hence the explicit
+/// `to_owned()` above and this `Cow`-like enum instead.
+pub enum MaybeOwnedCString<'a> {
+    Owned(CString),
+    Borrowed(BorrowedCString<'a>),
+}
+
+impl<'a> MaybeOwnedCString<'a> {
+    /// Return a pointer to the NUL-terminated string data.
+    pub fn as_ptr(&self) -> *const libc::c_char {
+        match *self {
+            MaybeOwnedCString::Owned(ref s) => s.as_ptr(),
+            MaybeOwnedCString::Borrowed(ref s) => s.as_ptr(),
+        }
+    }
+
+    /// Return the number of bytes in the string
+    /// (not including the NUL terminator).
+    pub fn len(&self) -> uint {
+        match *self {
+            MaybeOwnedCString::Owned(ref s) => s.len(),
+            MaybeOwnedCString::Borrowed(ref s) => s.len(),
+        }
+    }
+
+    /// Acquires an owned `CString`, copying the contents if currently
+    /// borrowed.
+    pub fn into_owned(self) -> CString {
+        match self {
+            MaybeOwnedCString::Owned(s) => s,
+            MaybeOwnedCString::Borrowed(s) => s.to_owned(),
+        }
+    }
 }
 
 impl Drop for CStrBuf {
@@ -530,9 +985,42 @@ impl Drop for CStrBuf {
     }
 }
 
+// Writes `bytes` as a double-quoted, C/ASCII-escaped string: printable
+// ASCII passes through, and everything else is rendered as `\n`, `\t`,
+// `\r`, `\\`, `\"`, or a `\xNN` escape. This gives a round-trippable,
+// unambiguous rendering of FFI string content that is not necessarily
+// valid UTF-8, unlike a lossy conversion to `String`.
+fn write_escaped(bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    try!(write!(f, "\""));
+    for &b in bytes.iter() {
+        match b {
+            b'\n' => try!(write!(f, "\\n")),
+            b'\t' => try!(write!(f, "\\t")),
+            b'\r' => try!(write!(f, "\\r")),
+            b'\\' => try!(write!(f, "\\\\")),
+            b'"' => try!(write!(f, "\\\"")),
+            0x20u8...0x7eu8 => try!(write!(f, "{}", b as char)),
+            _ => try!(write!(f, "\\x{:02x}", b)),
+        }
+    }
+    write!(f, "\"")
+}
+
 impl fmt::Show for CString {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        String::from_utf8_lossy(self.as_bytes_no_nul()).fmt(f)
+        write_escaped(self.as_bytes_no_nul(), f)
+    }
+}
+
+impl fmt::Show for CStrBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_escaped(self.to_vec().as_slice(), f)
+    }
+}
+
+impl<'a> fmt::Show for BorrowedCString<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_escaped(self.as_bytes_no_nul(), f)
     }
 }
 
@@ -547,6 +1035,19 @@ pub trait ToCStr for Sized? {
     /// Panics the task if the receiver has an interior null.
     fn to_c_str(&self) -> CString;
 
+    /// Copy the receiver into a `CString`, without panicking if the
+    /// receiver has an interior null.
+    ///
+    /// Returns a `NulError` reporting the offset of the interior null
+    /// byte instead of panicking.
+    fn try_to_c_str(&self) -> Result<CString, NulError>;
+
+    /// Alias for `try_to_c_str()`.
+    #[inline]
+    fn checked_to_c_str(&self) -> Result<CString, CStrError> {
+        self.try_to_c_str()
+    }
+
     /// Unsafe variant of `to_c_str()` that doesn't check for nulls.
     unsafe fn to_c_str_unchecked(&self) -> CString;
 
@@ -606,6 +1107,11 @@ impl ToCStr for str {
         self.as_bytes().to_c_str()
     }
 
+    #[inline]
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        self.as_bytes().try_to_c_str()
+    }
+
     #[inline]
     unsafe fn to_c_str_unchecked(&self) -> CString {
         self.as_bytes().to_c_str_unchecked()
@@ -638,6 +1144,11 @@ impl ToCStr for String {
         self.as_bytes().to_c_str()
     }
 
+    #[inline]
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        self.as_bytes().try_to_c_str()
+    }
+
     #[inline]
     unsafe fn to_c_str_unchecked(&self) -> CString {
         self.as_bytes().to_c_str_unchecked()
@@ -669,8 +1180,11 @@ const BUF_LEN: uint = 128;
 
 impl<'a> ToCStr for [u8] {
     fn to_c_str(&self) -> CString {
-        assert!(!self.contains(&NUL));
-        unsafe { self.to_c_str_unchecked() }
+        self.try_to_c_str().unwrap()
+    }
+
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        CString::from_bytes(self)
     }
 
     unsafe fn to_c_str_unchecked(&self) -> CString {
@@ -700,6 +1214,11 @@ impl<'a, Sized? T: ToCStr> ToCStr for &'a T {
         (**self).to_c_str()
     }
 
+    #[inline]
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        (**self).try_to_c_str()
+    }
+
     #[inline]
     unsafe fn to_c_str_unchecked(&self) -> CString {
         (**self).to_c_str_unchecked()
@@ -746,7 +1265,7 @@ unsafe fn with_c_str_len<T>(v: &[u8], checked: bool,
     let len = v.len();
     let c_str = if len < BUF_LEN {
         if checked {
-            assert!(!v.contains(&NUL));
+            assert!(first_nul(v).is_none());
         }
         let mut buf: [u8, .. BUF_LEN] = mem::uninitialized();
         slice::bytes::copy_memory(&mut buf, v);
@@ -774,8 +1293,13 @@ impl ToCStr for CStrBuf {
         unsafe { self.to_c_str_unchecked() }
     }
 
+    #[inline]
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        Ok(unsafe { self.to_c_str_unchecked() })
+    }
+
     unsafe fn to_c_str_unchecked(&self) -> CString {
-        str_dup(self.ptr as *const u8, libc::strlen(self.ptr) as uint)
+        str_dup(self.ptr as *const u8, c_strlen(self.ptr))
     }
 
     fn with_c_str<T>(&self, f: |*const libc::c_char| -> T) -> T {
@@ -787,12 +1311,12 @@ impl ToCStr for CStrBuf {
     }
 
     fn with_c_str_len<T>(&self, f: |*const libc::c_char, uint| -> T) -> T {
-        let len = unsafe { libc::strlen(self.ptr) as uint };
+        let len = unsafe { c_strlen(self.ptr) };
         f(self.ptr, len)
     }
 
     unsafe fn with_c_str_len_unchecked<T>(&self, f: |*const libc::c_char, uint| -> T) -> T {
-        let len = libc::strlen(self.ptr) as uint;
+        let len = c_strlen(self.ptr);
         f(self.ptr, len)
     }
 }
@@ -804,6 +1328,11 @@ impl ToCStr for CString {
         unsafe { self.to_c_str_unchecked() }
     }
 
+    #[inline]
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        Ok(unsafe { self.to_c_str_unchecked() })
+    }
+
     unsafe fn to_c_str_unchecked(&self) -> CString {
         str_dup(self.buf.ptr as *const u8, self.len)
     }
@@ -832,6 +1361,11 @@ impl<'a> ToCStr for BorrowedCString<'a> {
         unsafe { self.to_c_str_unchecked() }
     }
 
+    #[inline]
+    fn try_to_c_str(&self) -> Result<CString, NulError> {
+        Ok(unsafe { self.to_c_str_unchecked() })
+    }
+
     unsafe fn to_c_str_unchecked(&self) -> CString {
         str_dup(self.ptr as *const u8, self.len)
     }
@@ -884,6 +1418,54 @@ impl<'a> Iterator<libc::c_char> for CChars<'a> {
     }
 }
 
+/// External iterator over the NUL-separated entries of a C
+/// "multistring", eg windows env values or the req->ptr result in a
+/// uv_fs_readdir() call.
+///
+/// Iteration stops at the first empty entry, i.e. the double NUL that
+/// terminates a multistring, or after an optional maximum count of
+/// entries is reached.
+///
+/// Use with the `std::iter` module.
+pub struct CMultiString<'a> {
+    ptr: *const libc::c_char,
+    count: uint,
+    limit: Option<uint>,
+    marker: marker::ContravariantLifetime<'a>,
+}
+
+/// Wraps a pointer to a C "multistring" into a `CMultiString` iterator.
+///
+/// Optionally, a `count` can be passed in, limiting the iterator to
+/// yielding at most `count` entries.
+pub unsafe fn multistring<'a>(buf: *const libc::c_char,
+                              count: Option<uint>) -> CMultiString<'a> {
+    CMultiString {
+        ptr: buf,
+        count: 0,
+        limit: count,
+        marker: marker::ContravariantLifetime,
+    }
+}
+
+impl<'a> Iterator<BorrowedCString<'a>> for CMultiString<'a> {
+    fn next(&mut self) -> Option<BorrowedCString<'a>> {
+        match self.limit {
+            Some(limit) if self.count >= limit => return None,
+            _ => ()
+        }
+        unsafe {
+            if *self.ptr == 0 {
+                return None;
+            }
+            let cstr = BorrowedCString::wrap(self.ptr);
+            self.ptr = self.ptr.offset(cstr.len() as int + 1);
+            self.count += 1;
+            Some(cstr)
+        }
+    }
+}
+
 /// Parses a C "multistring", eg windows env values or
 /// the req->ptr result in a uv_fs_readdir() call.
 ///
@@ -895,21 +1477,12 @@ impl<'a> Iterator<libc::c_char> for CChars<'a> {
 pub unsafe fn from_c_multistring(buf: *const libc::c_char,
                                  count: Option<uint>,
                                  f: for<'a> |BorrowedCString<'a>|) -> uint {
-
-    let mut curr_ptr = buf;
-    let mut ctr = 0;
-    let (limited_count, limit) = match count {
-        Some(limit) => (true, limit),
-        None => (false, 0)
-    };
-    while (!limited_count || ctr < limit)
-          && *curr_ptr != 0 {
-        let cstr = CStrBuf::new_unowned(curr_ptr).into_c_str();
-        f(cstr.borrow());
-        curr_ptr = curr_ptr.offset(cstr.len() as int + 1);
+    let mut ctr = 0u;
+    for cstr in multistring(buf, count) {
+        f(cstr);
         ctr += 1;
     }
-    return ctr;
+    ctr
 }
 
 #[cfg(test)]
@@ -924,9 +1497,10 @@ mod tests {
     use std::task;
     use libc;
 
-    use super::{CStrBuf,CString,BorrowedCString,ToCStr};
+    use super::{CStrBuf,CString,CStrError,BorrowedCString,MaybeOwnedCString,ToCStr};
     use super::from_c_multistring;
-    use super::buf_dup;
+    use super::multistring;
+    use super::{buf_dup,first_nul};
 
     fn c_buf_from_bytes(v: &[u8]) -> CStrBuf {
         unsafe { buf_dup(v.as_ptr(), v.len()) }
@@ -948,6 +1522,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_multistring_iterator() {
+        unsafe {
+            let input = b"zero\0one\0\0";
+            let ptr = input.as_ptr();
+            let mut it = multistring(ptr as *const libc::c_char, None);
+            assert_eq!(it.next().unwrap().as_bytes_no_nul(), b"zero");
+            assert_eq!(it.next().unwrap().as_bytes_no_nul(), b"one");
+            assert!(it.next().is_none());
+        }
+    }
+
+    #[test]
+    fn test_multistring_iterator_limit() {
+        unsafe {
+            let input = b"zero\0one\0two\0\0";
+            let ptr = input.as_ptr();
+            let entries: Vec<BorrowedCString> =
+                multistring(ptr as *const libc::c_char, Some(2)).collect();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].as_bytes_no_nul(), b"zero");
+            assert_eq!(entries[1].as_bytes_no_nul(), b"one");
+        }
+    }
+
     #[test]
     fn test_str_to_c_str() {
         let c_str = "".to_c_str();
@@ -1170,6 +1769,72 @@ mod tests {
         assert!(task::try(proc() { "he\x00llo".to_c_str() }).is_err());
     }
 
+    #[test]
+    fn test_c_buf_to_vec_long() {
+        // Long enough to span several machine words, to exercise the
+        // word-at-a-time length computation in `c_strlen`.
+        let long = String::from_char(257, 'x');
+        let c_buf = c_buf_from_bytes(long.as_bytes());
+        assert_eq!(c_buf.to_vec(), long.into_bytes());
+    }
+
+    #[test]
+    fn test_first_nul() {
+        let exp: Option<uint> = None;
+        assert_eq!(first_nul(b""), exp);
+        assert_eq!(first_nul(b"hello"), exp);
+        assert_eq!(first_nul(b"\x00"), Some(0));
+        assert_eq!(first_nul(b"he\x00llo"), Some(2));
+
+        // A buffer long enough to span several machine words, to
+        // exercise the aligned word-at-a-time path along with the
+        // unaligned head/tail scans.
+        let long: Vec<u8> = Vec::from_fn(257, |i| (i % 255 + 1) as u8);
+        assert_eq!(first_nul(long.as_slice()), exp);
+        for &pos in [0u, 1, 7, 8, 63, 128, 256].iter() {
+            let mut v = long.clone();
+            v[pos] = 0;
+            assert_eq!(first_nul(v.as_slice()), Some(pos));
+        }
+
+        // Unaligned head: a sub-slice whose start is not word-aligned.
+        assert_eq!(first_nul(long.slice_from(1)), exp);
+    }
+
+    #[test]
+    fn test_from_bytes_fail() {
+        let err = CString::from_bytes(b"he\x00llo").err().unwrap();
+        assert_eq!(err.nul_position(), 2);
+        assert_eq!(err.into_bytes(), b"he\x00llo".to_vec());
+    }
+
+    #[test]
+    fn test_from_bytes_ok() {
+        let c_str = CString::from_bytes(b"hello").ok().unwrap();
+        assert_eq!(c_str.as_bytes(), b"hello\0");
+    }
+
+    #[test]
+    fn test_try_to_c_str_fail() {
+        let err = "he\x00llo".try_to_c_str().err().unwrap();
+        assert_eq!(err.nul_position(), 2);
+    }
+
+    #[test]
+    fn test_try_to_c_str_ok() {
+        let c_str = "hello".try_to_c_str().ok().unwrap();
+        assert_eq!(c_str.as_bytes(), b"hello\0");
+    }
+
+    #[test]
+    fn test_checked_to_c_str() {
+        let err: CStrError = "he\x00llo".checked_to_c_str().err().unwrap();
+        assert_eq!(err.nul_position(), 2);
+
+        let c_str = "hello".checked_to_c_str().ok().unwrap();
+        assert_eq!(c_str.as_bytes(), b"hello\0");
+    }
+
     #[test]
     fn test_to_c_str_unchecked() {
         unsafe {
@@ -1303,6 +1968,163 @@ mod tests {
         let _c_str = get_inner_str(&c);
     }
 
+    #[test]
+    fn test_show_escaping() {
+        let c_str = unsafe { b"he\x00llo".to_c_str_unchecked() };
+        assert_eq!(format!("{}", c_str).as_slice(), "\"he\\x00llo\"");
+
+        let c_str = "tab\tnewline\nback\\slash".to_c_str();
+        assert_eq!(format!("{}", c_str).as_slice(),
+                   "\"tab\\tnewline\\nback\\\\slash\"");
+
+        let c_str = b"foo\xFF".to_c_str();
+        assert_eq!(format!("{}", c_str).as_slice(), "\"foo\\xff\"");
+    }
+
+    #[test]
+    fn test_ref_show_escaping() {
+        let c_buf = c_buf_from_bytes(b"foo\xFF");
+        let c_ref = c_buf.borrow_with_len();
+        assert_eq!(format!("{}", c_ref).as_slice(), "\"foo\\xff\"");
+    }
+
+    #[test]
+    fn test_buf_show_escaping() {
+        let c_buf = c_buf_from_bytes(b"foo\xFF");
+        assert_eq!(format!("{}", c_buf).as_slice(), "\"foo\\xff\"");
+    }
+
+    #[test]
+    fn test_from_vec_with_nul_ok() {
+        let v = b"hello\0".to_vec();
+        let c_str = CString::from_vec_with_nul(v).ok().unwrap();
+        assert_eq!(c_str.as_bytes(), b"hello\0");
+    }
+
+    #[test]
+    fn test_from_vec_with_nul_fail_not_terminated() {
+        let v = b"hello".to_vec();
+        let err = CString::from_vec_with_nul(v).err().unwrap();
+        assert_eq!(err.into_bytes(), b"hello".to_vec());
+    }
+
+    #[test]
+    fn test_from_vec_with_nul_fail_interior_nul() {
+        let v = b"he\x00llo\0".to_vec();
+        let err = CString::from_vec_with_nul(v).err().unwrap();
+        assert_eq!(err.into_bytes(), b"he\x00llo\0".to_vec());
+    }
+
+    #[test]
+    fn test_from_vec_with_nul_unchecked() {
+        let v = b"hello\0".to_vec();
+        let c_str = unsafe { CString::from_vec_with_nul_unchecked(v) };
+        assert_eq!(c_str.as_bytes(), b"hello\0");
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let c_str = "hello".to_c_str();
+        assert_eq!(c_str.into_vec(), b"hello".to_vec());
+    }
+
+    #[test]
+    fn test_into_bytes_with_nul() {
+        let c_str = "hello".to_c_str();
+        assert_eq!(c_str.into_bytes_with_nul(), b"hello\0".to_vec());
+    }
+
+    #[test]
+    fn test_into_string() {
+        let c_str = "hello".to_c_str();
+        assert_eq!(c_str.into_string().ok().unwrap(), String::from_str("hello"));
+    }
+
+    #[test]
+    fn test_into_string_fail() {
+        let c_str = b"foo\xFF".to_c_str();
+        let err = c_str.into_string().err().unwrap();
+        let c_str = err.into_c_string();
+        assert_eq!(c_str.as_bytes_no_nul(), b"foo\xFF");
+    }
+
+    #[test]
+    fn test_ref_ord() {
+        let a = c_buf_from_bytes(b"abc");
+        let b = c_buf_from_bytes(b"abd");
+        assert!(a.borrow_with_len() < b.borrow_with_len());
+        assert!(a.borrow_with_len() == a.borrow_with_len());
+        assert!(a.borrow_with_len() != b.borrow_with_len());
+    }
+
+    #[test]
+    fn test_buf_hashable() {
+        use std::hash::hash;
+        let a = c_buf_from_bytes(b"hello");
+        let b = c_buf_from_bytes(b"hello");
+        assert_eq!(hash(&a), hash(&b));
+    }
+
+    #[test]
+    fn test_ref_hashable() {
+        use std::hash::hash;
+        let a = c_buf_from_bytes(b"hello");
+        let b = c_buf_from_bytes(b"hello");
+        assert_eq!(hash(&a.borrow_with_len()), hash(&b.borrow_with_len()));
+    }
+
+    #[test]
+    fn test_cross_type_eq() {
+        let c_str = "hello".to_c_str();
+        let hello_bytes: &[u8] = b"hello";
+        assert!(c_str == "hello");
+        assert!("hello" == c_str);
+        assert!(c_str == hello_bytes);
+        assert!(c_str == String::from_str("hello"));
+        assert!(c_str == b"hello".to_vec());
+        assert!(c_str != "world");
+
+        let c_buf = c_buf_from_bytes(b"hello");
+        assert!(c_buf == "hello");
+        assert!(c_buf == String::from_str("hello"));
+
+        let c_ref = c_buf.borrow_with_len();
+        assert!(c_ref == "hello");
+        assert!("hello" == c_ref);
+        assert!(c_ref == b"hello".to_vec());
+    }
+
+    #[test]
+    fn test_cross_type_ord() {
+        let c_str = "bbb".to_c_str();
+        assert!(c_str > "aaa");
+        assert!("aaa" < c_str);
+        assert!(c_str < "ccc");
+    }
+
+    #[test]
+    fn test_ref_to_owned() {
+        let c_buf = c_buf_from_bytes(b"hello");
+        let c_ref = c_buf.borrow_with_len();
+        let owned = c_ref.to_owned();
+        assert_eq!(owned.as_bytes(), b"hello\0");
+    }
+
+    #[test]
+    fn test_maybe_owned_c_string() {
+        let c_buf = c_buf_from_bytes(b"hello");
+        let c_ref = c_buf.borrow_with_len();
+        let maybe = MaybeOwnedCString::Borrowed(c_ref);
+        assert_eq!(maybe.len(), 5);
+        let owned = maybe.into_owned();
+        assert_eq!(owned.as_bytes(), b"hello\0");
+
+        let maybe = MaybeOwnedCString::Owned("world".to_c_str());
+        assert_eq!(maybe.len(), 5);
+        let owned = maybe.into_owned();
+        assert_eq!(owned.as_bytes(), b"world\0");
+    }
+
     #[test]
     fn test_into_c_str() {
         let buf = c_buf_from_bytes(b"hello");
@@ -1480,4 +2302,26 @@ mod bench {
     fn bench_with_c_str_len_unchecked_long(b: &mut Bencher) {
         bench_with_c_str_len_unchecked(b, S_LONG)
     }
+
+    fn bench_first_nul(b: &mut Bencher, s: &str) {
+        let bytes = s.as_bytes();
+        b.iter(|| {
+            super::first_nul(bytes)
+        })
+    }
+
+    #[bench]
+    fn bench_first_nul_short(b: &mut Bencher) {
+        bench_first_nul(b, S_SHORT)
+    }
+
+    #[bench]
+    fn bench_first_nul_medium(b: &mut Bencher) {
+        bench_first_nul(b, S_MEDIUM)
+    }
+
+    #[bench]
+    fn bench_first_nul_long(b: &mut Bencher) {
+        bench_first_nul(b, S_LONG)
+    }
 }